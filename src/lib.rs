@@ -0,0 +1,721 @@
+use std::env;
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use tempfile::{Builder, TempDir};
+use which::which;
+
+#[warn(unused_macros)]
+macro_rules! quote_arg {
+    ($arg:expr) => {
+        if $arg.contains(' ') {
+            format!("\"{}\"", $arg)
+        } else {
+            format!("{}", $arg)
+        }
+    };
+}
+
+/// Errors that can occur while building or running a [`RandomTemp`] shim.
+#[derive(Debug)]
+pub enum Error {
+    /// Could not work out which real executable to pretend to be.
+    ExecutableNotFound(String),
+    /// `RANDOMTEMP_BASEDIR` (or an explicit `base_dir`) doesn't exist.
+    InvalidBaseDir(String),
+    /// `RANDOMTEMP_MAXTRIAL` isn't a valid number in `0..256`.
+    InvalidMaxTrial,
+    /// `RANDOMTEMP_RANDLEN` isn't a valid number.
+    InvalidRandLen,
+    /// `RANDOMTEMP_RETRY_CODES` contains something other than a comma-separated
+    /// list of numbers.
+    InvalidRetryCodes,
+    /// Failed to query the current executable or working directory.
+    Env(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::ExecutableNotFound(msg) => write!(f, "{}", msg),
+            Error::InvalidBaseDir(msg) => write!(f, "{}", msg),
+            Error::InvalidMaxTrial => {
+                write!(f, "RANDOMTEMP_MAXTRIAL is not valid number in 0..256")
+            }
+            Error::InvalidRandLen => write!(f, "RANDOMTEMP_RANDLEN is not a valid number"),
+            Error::InvalidRetryCodes => write!(
+                f,
+                "RANDOMTEMP_RETRY_CODES is not a comma-separated list of numbers"
+            ),
+            Error::Env(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+fn path_exists(path: &str) -> bool {
+    fs::metadata(path).is_ok()
+}
+
+fn dir_exists(path: &str) -> bool {
+    path_exists(path) && fs::metadata(path).unwrap().is_dir()
+}
+
+fn is_absolute_path(path: &str) -> bool {
+    Path::new(path).is_absolute()
+}
+
+fn find_executable_in_path_by_name(p: &PathBuf, cp: &PathBuf) -> Option<PathBuf> {
+    let f = get_file_name(Some(p));
+    f.and_then(|s| {
+        which(s)
+            .ok()
+            // TODO: We should try to find another one instead of
+            // skipping.
+            .and_then(|np| if &np == cp { None } else { Some(np) })
+    })
+}
+
+fn find_executable_in_path() -> Result<PathBuf, String> {
+    let p = get_current_exe_pathbuf()?;
+    find_executable_in_path_by_name(&p, &p).ok_or_else(|| {
+        "Cannot find which executable to pretend, either specify \
+		RANDOMTEMP_EXECUTABLE through the environmental variables \
+	 	or rename the executable to another one in PATH"
+            .to_string()
+    })
+}
+
+fn find_executable_in_path_by_env(exec: &str) -> Result<PathBuf, String> {
+    let p = PathBuf::from(&exec);
+    let cp = get_current_exe_pathbuf()?;
+    if is_same_file_stem(Some(&p), Some(&cp)) {
+        // Stop pretending self
+        return Err(String::new());
+    }
+    find_executable_in_path_by_name(&p, &cp)
+        .or_else(|| {
+            if p.extension().is_some() {
+                None
+            } else {
+                Some(p)
+            }
+        })
+        .ok_or_else(|| "RANDOMTEMP_EXECUTABLE points to an invalid executable".to_string())
+}
+
+fn get_current_exe_pathbuf() -> Result<PathBuf, String> {
+    env::current_exe().map_err(|_| "Cannot get the current working executable".to_string())
+}
+
+fn get_current_dir_pathbuf() -> Result<PathBuf, String> {
+    env::current_dir().map_err(|_| "Cannot get the current working directory".to_string())
+}
+
+#[allow(dead_code)]
+fn get_current_exe() -> Result<String, String> {
+    let pb = get_current_exe_pathbuf()?;
+    let p = pb
+        .to_str()
+        .ok_or("Cannot convert the current working directory to a UTF-8 string")?;
+    Ok(String::from(p))
+}
+
+fn get_current_dir() -> Result<String, String> {
+    let pb = get_current_dir_pathbuf()?;
+    let p = pb
+        .to_str()
+        .ok_or("Cannot convert the current working directory to a UTF-8 string")?;
+    Ok(String::from(p))
+}
+
+fn get_file_name(pbopt: Option<&PathBuf>) -> Option<&OsStr> {
+    let pb = pbopt?;
+    pb.file_name()
+}
+
+fn get_file_stem(pbopt: Option<&PathBuf>) -> Option<&OsStr> {
+    let pb = pbopt?;
+    pb.file_stem()
+}
+
+#[allow(dead_code)]
+fn is_same_file_pathbuf(pbopt1: Option<&PathBuf>, pbopt2: Option<&PathBuf>) -> bool {
+    if let (Some(f1), Some(f2)) = (pbopt1, pbopt2) {
+        return f1 == f2;
+    }
+    false
+}
+
+fn is_same_file_stem(pbopt1: Option<&PathBuf>, pbopt2: Option<&PathBuf>) -> bool {
+    let fs1 = get_file_stem(pbopt1);
+    let fs2 = get_file_stem(pbopt2);
+    if let (Some(fs1), Some(fs2)) = (fs1, fs2) {
+        return fs1 == fs2;
+    }
+    false
+}
+
+fn get_pretend_executable() -> Result<String, Error> {
+    let found = match env::var("RANDOMTEMP_EXECUTABLE") {
+        Ok(exec) => {
+            if is_absolute_path(&exec) {
+                Some(PathBuf::from(exec))
+            } else {
+                match find_executable_in_path_by_env(&exec) {
+                    Ok(p) => Some(p),
+                    Err(e) => {
+                        if e.is_empty() {
+                            None
+                        } else {
+                            return Err(Error::ExecutableNotFound(e));
+                        }
+                    }
+                }
+            }
+        }
+        Err(_) => None,
+    };
+
+    let found = match found {
+        Some(p) => p,
+        None => find_executable_in_path().map_err(Error::ExecutableNotFound)?,
+    };
+
+    found
+        .to_str()
+        .map(String::from)
+        .ok_or_else(|| Error::ExecutableNotFound(
+            "Cannot convert RANDOMTEMP_EXECUTABLE to a UTF-8 string".to_string(),
+        ))
+}
+
+fn get_base_dir() -> Result<String, Error> {
+    match env::var("RANDOMTEMP_BASEDIR") {
+        Ok(path) => {
+            if dir_exists(&path) {
+                Ok(path)
+            } else {
+                Err(Error::InvalidBaseDir(
+                    "The directory specified in RANDOMTEMP_BASEDIR doesn't exist".to_string(),
+                ))
+            }
+        }
+        Err(_) => get_current_dir().map_err(Error::Env),
+    }
+}
+
+fn get_max_trial() -> Result<u8, Error> {
+    const DEFAULT_MAX_TRIAL: u8 = 3;
+
+    match env::var("RANDOMTEMP_MAXTRIAL") {
+        Ok(val) => val.parse().map_err(|_| Error::InvalidMaxTrial),
+        Err(_) => Ok(DEFAULT_MAX_TRIAL),
+    }
+}
+
+fn get_temp_prefix() -> String {
+    // Matches tempfile's own default prefix so existing scratch directories
+    // keep their familiar `.tmpXXXXXX` shape when the env var is unset.
+    const DEFAULT_PREFIX: &str = ".tmp";
+
+    env::var("RANDOMTEMP_PREFIX").unwrap_or_else(|_| DEFAULT_PREFIX.to_string())
+}
+
+fn get_temp_suffix() -> String {
+    env::var("RANDOMTEMP_SUFFIX").unwrap_or_default()
+}
+
+fn get_temp_randlen() -> Result<usize, Error> {
+    const DEFAULT_RAND_LEN: usize = 6;
+
+    match env::var("RANDOMTEMP_RANDLEN") {
+        Ok(val) => val.parse().map_err(|_| Error::InvalidRandLen),
+        Err(_) => Ok(DEFAULT_RAND_LEN),
+    }
+}
+
+fn create_temp_dir(cwd: &str, prefix: &str, suffix: &str, rand_len: usize) -> io::Result<TempDir> {
+    Builder::new()
+        .prefix(prefix)
+        .suffix(suffix)
+        .rand_bytes(rand_len)
+        .tempdir_in(&cwd)
+}
+
+#[cfg(windows)]
+const DEFAULT_ENV_TARGETS: &[&str] = &["TEMP", "TMP"];
+
+#[cfg(not(windows))]
+const DEFAULT_ENV_TARGETS: &[&str] = &["TMPDIR"];
+
+fn get_retry_codes() -> Result<Option<Vec<i32>>, Error> {
+    match env::var("RANDOMTEMP_RETRY_CODES") {
+        Ok(val) if val.trim().is_empty() => Ok(None),
+        Ok(val) => val
+            .split(',')
+            .map(|code| code.trim().parse().map_err(|_| Error::InvalidRetryCodes))
+            .collect::<Result<Vec<i32>, Error>>()
+            .map(Some),
+        Err(_) => Ok(None),
+    }
+}
+
+fn get_keep_on_failure() -> bool {
+    match env::var("RANDOMTEMP_KEEP") {
+        Ok(val) => !matches!(val.to_lowercase().as_str(), "" | "0" | "false"),
+        Err(_) => false,
+    }
+}
+
+fn get_env_targets() -> Vec<String> {
+    match env::var("RANDOMTEMP_ENVVARS") {
+        Ok(val) => env::split_paths(&val)
+            .filter_map(|p| p.to_str().map(String::from))
+            .collect(),
+        Err(_) => DEFAULT_ENV_TARGETS.iter().map(|s| s.to_string()).collect(),
+    }
+}
+
+/// Splits `targets` into plain env-var names and `{}` templates (e.g.
+/// `--tmp={}`), substituting `tmp_path` into each template.
+fn resolve_env_targets(targets: &[String], tmp_path: &Path) -> (Vec<String>, Vec<String>) {
+    let mut env_vars = Vec::new();
+    let mut extra_args = Vec::new();
+    for target in targets {
+        if target.contains("{}") {
+            extra_args.push(target.replace("{}", &tmp_path.to_string_lossy()));
+        } else {
+            env_vars.push(target.clone());
+        }
+    }
+    (env_vars, extra_args)
+}
+
+/// A configured `randomtemp` shim, ready to spawn the pretended executable
+/// with a fresh scratch directory.
+///
+/// Build one with [`RandomTemp::new`] or [`RandomTemp::from_env`], then call
+/// [`RandomTemp::run`].
+pub struct RandomTemp {
+    executable: String,
+    base_dir: String,
+    max_trial: u8,
+    prefix: String,
+    suffix: String,
+    rand_len: usize,
+    env_targets: Vec<String>,
+    keep_on_failure: bool,
+    retry_codes: Option<Vec<i32>>,
+}
+
+/// Fluent builder for [`RandomTemp`].
+pub struct RandomTempBuilder {
+    executable: Option<String>,
+    base_dir: Option<String>,
+    max_trial: Option<u8>,
+    prefix: Option<String>,
+    suffix: Option<String>,
+    rand_len: Option<usize>,
+    env_targets: Option<Vec<String>>,
+    keep_on_failure: Option<bool>,
+    retry_codes: Option<Option<Vec<i32>>>,
+}
+
+impl RandomTemp {
+    /// Starts building a `RandomTemp` with no fields set; unset fields fall
+    /// back to the same defaults `from_env` uses.
+    #[allow(clippy::new_ret_no_self)] // intentional fluent-builder entry point
+    pub fn new() -> RandomTempBuilder {
+        RandomTempBuilder {
+            executable: None,
+            base_dir: None,
+            max_trial: None,
+            prefix: None,
+            suffix: None,
+            rand_len: None,
+            env_targets: None,
+            keep_on_failure: None,
+            retry_codes: None,
+        }
+    }
+
+    /// Builds a `RandomTemp` straight from the `RANDOMTEMP_*` environment
+    /// variables, the same way `main` used to.
+    pub fn from_env() -> Result<RandomTemp, Error> {
+        Ok(RandomTemp {
+            executable: get_pretend_executable()?,
+            base_dir: get_base_dir()?,
+            max_trial: get_max_trial()?,
+            prefix: get_temp_prefix(),
+            suffix: get_temp_suffix(),
+            rand_len: get_temp_randlen()?,
+            env_targets: get_env_targets(),
+            keep_on_failure: get_keep_on_failure(),
+            retry_codes: get_retry_codes()?,
+        })
+    }
+
+    #[cfg(windows)]
+    fn try_run_with_new_temp(&self) -> io::Result<process::ExitStatus> {
+        let tmp_dir = create_temp_dir(
+            &self.base_dir,
+            &self.prefix,
+            &self.suffix,
+            self.rand_len,
+        )?;
+        let tmp_path = tmp_dir.path().to_path_buf();
+        let (env_vars, extra_args) = resolve_env_targets(&self.env_targets, &tmp_path);
+        let status = if is_absolute_path(&self.executable) {
+            let mut cmd = process::Command::new(&self.executable);
+            for var in &env_vars {
+                cmd.env(var, &tmp_path);
+            }
+            cmd.args(env::args().skip(1)).args(&extra_args).status()?
+        } else {
+            // We need to quote the args here for Command Prompt
+            // Below is the workground given in
+            // https://internals.rust-lang.org/t/std-process-on-windows-is-escaping-raw-literals-which-causes-problems-with-chaining-commands/8163/16
+            let mut args = String::new();
+            args.push_str(quote_arg!(self.executable).as_str());
+            for arg in env::args().skip(1) {
+                args.push(' ');
+                args.push_str(quote_arg!(arg).as_str());
+            }
+            for arg in &extra_args {
+                args.push(' ');
+                args.push_str(quote_arg!(arg).as_str());
+            }
+            let arg_name = "RANDOMTEMP_COMMANDLINE";
+            let mut cmd = process::Command::new("cmd");
+            cmd.arg("/q").arg("/c");
+            for var in &env_vars {
+                cmd.env(var, &tmp_path);
+            }
+            cmd.env(arg_name, args.as_str())
+                .arg(format!("%{}%", arg_name))
+                .status()?
+        };
+        self.keep_temp_dir_on_failure(tmp_dir, &status);
+        Ok(status)
+    }
+
+    #[cfg(not(windows))]
+    fn try_run_with_new_temp(&self) -> io::Result<process::ExitStatus> {
+        let tmp_dir = create_temp_dir(
+            &self.base_dir,
+            &self.prefix,
+            &self.suffix,
+            self.rand_len,
+        )?;
+        let tmp_path = tmp_dir.path().to_path_buf();
+        let (env_vars, extra_args) = resolve_env_targets(&self.env_targets, &tmp_path);
+        let status = if is_absolute_path(&self.executable) {
+            let mut cmd = process::Command::new(&self.executable);
+            for var in &env_vars {
+                cmd.env(var, &tmp_path);
+            }
+            cmd.args(env::args().skip(1)).args(&extra_args).status()?
+        } else {
+            let mut cmd = process::Command::new("sh");
+            cmd.arg("-c");
+            for var in &env_vars {
+                cmd.env(var, &tmp_path);
+            }
+            // `sh -c '<script>' <$0> <args...>` only forwards <args...> to
+            // the child if the script text itself expands "$@", and
+            // whatever follows the script becomes $0, not part of $@ — so
+            // the script has to be `<executable> "$@"`, with the
+            // executable name repeated as a dummy $0.
+            cmd.arg(format!("{} \"$@\"", self.executable))
+                .arg(&self.executable)
+                .args(env::args().skip(1))
+                .args(&extra_args)
+                .status()?
+        };
+        self.keep_temp_dir_on_failure(tmp_dir, &status);
+        Ok(status)
+    }
+
+    /// If `keep_on_failure` is set and `status` is non-zero, persists
+    /// `tmp_dir` to disk instead of letting it clean up on drop, and prints
+    /// its location so it can be inspected.
+    fn keep_temp_dir_on_failure(&self, tmp_dir: TempDir, status: &process::ExitStatus) {
+        if self.keep_on_failure && !status.success() {
+            let path = tmp_dir.keep();
+            eprintln!("Keeping temporary directory: {}", path.display());
+        }
+    }
+
+    /// Runs the create-temp / set-env / spawn / retry loop, returning the
+    /// final child's exit status.
+    pub fn run(&self) -> io::Result<process::ExitStatus> {
+        let mut retry_times: u8 = 0;
+
+        loop {
+            if retry_times > 0 {
+                println!("Retry attempt: {}", retry_times);
+            }
+            let status = self.try_run_with_new_temp()?;
+            retry_times += 1;
+            if status.success() || retry_times > self.max_trial {
+                return Ok(status);
+            }
+            if let Some(retryable_codes) = &self.retry_codes {
+                // A `None` code means the child died to a signal rather than
+                // exiting normally; that's not one of the configured exit
+                // codes, so don't retry it.
+                match status.code() {
+                    Some(code) if retryable_codes.contains(&code) => {}
+                    _ => return Ok(status),
+                }
+            }
+        }
+    }
+}
+
+impl RandomTempBuilder {
+    /// Sets the executable to pretend to be (mirrors `RANDOMTEMP_EXECUTABLE`).
+    pub fn executable<S: Into<String>>(mut self, executable: S) -> Self {
+        self.executable = Some(executable.into());
+        self
+    }
+
+    /// Sets the directory new scratch directories are created in (mirrors
+    /// `RANDOMTEMP_BASEDIR`).
+    pub fn base_dir<S: Into<String>>(mut self, base_dir: S) -> Self {
+        self.base_dir = Some(base_dir.into());
+        self
+    }
+
+    /// Sets the maximum number of retries (mirrors `RANDOMTEMP_MAXTRIAL`).
+    pub fn max_trials(mut self, max_trial: u8) -> Self {
+        self.max_trial = Some(max_trial);
+        self
+    }
+
+    /// Sets the temp-dir name prefix (mirrors `RANDOMTEMP_PREFIX`).
+    pub fn prefix<S: Into<String>>(mut self, prefix: S) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    /// Sets the temp-dir name suffix (mirrors `RANDOMTEMP_SUFFIX`).
+    pub fn suffix<S: Into<String>>(mut self, suffix: S) -> Self {
+        self.suffix = Some(suffix.into());
+        self
+    }
+
+    /// Sets the number of random bytes in the temp-dir name (mirrors
+    /// `RANDOMTEMP_RANDLEN`).
+    pub fn rand_len(mut self, rand_len: usize) -> Self {
+        self.rand_len = Some(rand_len);
+        self
+    }
+
+    /// Sets the list of env vars (and/or `{}` argument templates, e.g.
+    /// `--tmp={}`) that get pointed at the fresh temp directory (mirrors
+    /// `RANDOMTEMP_ENVVARS`).
+    pub fn env_vars<I: IntoIterator<Item = S>, S: Into<String>>(mut self, env_targets: I) -> Self {
+        self.env_targets = Some(env_targets.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Sets whether a failing child's temp directory is kept on disk instead
+    /// of being cleaned up (mirrors `RANDOMTEMP_KEEP`).
+    pub fn keep_on_failure(mut self, keep_on_failure: bool) -> Self {
+        self.keep_on_failure = Some(keep_on_failure);
+        self
+    }
+
+    /// Restricts retries to the given exit codes (mirrors
+    /// `RANDOMTEMP_RETRY_CODES`). Without this, any non-zero exit is
+    /// retried, same as today.
+    pub fn retry_codes<I: IntoIterator<Item = i32>>(mut self, retry_codes: I) -> Self {
+        self.retry_codes = Some(Some(retry_codes.into_iter().collect()));
+        self
+    }
+
+    /// Finishes building, resolving any unset field the same way
+    /// [`RandomTemp::from_env`] would default it, and validating `base_dir`.
+    pub fn build(self) -> Result<RandomTemp, Error> {
+        let executable = match self.executable {
+            Some(executable) => executable,
+            None => get_pretend_executable()?,
+        };
+        let base_dir = match self.base_dir {
+            Some(base_dir) => {
+                if dir_exists(&base_dir) {
+                    base_dir
+                } else {
+                    return Err(Error::InvalidBaseDir(
+                        "The specified base_dir doesn't exist".to_string(),
+                    ));
+                }
+            }
+            None => get_base_dir()?,
+        };
+        let max_trial = match self.max_trial {
+            Some(max_trial) => max_trial,
+            None => get_max_trial()?,
+        };
+        let prefix = self.prefix.unwrap_or_else(get_temp_prefix);
+        let suffix = self.suffix.unwrap_or_else(get_temp_suffix);
+        let rand_len = match self.rand_len {
+            Some(rand_len) => rand_len,
+            None => get_temp_randlen()?,
+        };
+        let env_targets = self.env_targets.unwrap_or_else(get_env_targets);
+        let keep_on_failure = self.keep_on_failure.unwrap_or_else(get_keep_on_failure);
+        let retry_codes = match self.retry_codes {
+            Some(retry_codes) => retry_codes,
+            None => get_retry_codes()?,
+        };
+
+        Ok(RandomTemp {
+            executable,
+            base_dir,
+            max_trial,
+            prefix,
+            suffix,
+            rand_len,
+            env_targets,
+            keep_on_failure,
+            retry_codes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_exists_relative_pass() {
+        assert_eq!(path_exists("."), true);
+    }
+
+    #[test]
+    fn test_dir_exists_relative_pass() {
+        assert_eq!(dir_exists("."), true);
+    }
+    #[test]
+    fn test_path_exists_absolute_pass() {
+        let e = get_current_exe().ok();
+        e.and_then(|p| {
+            assert_eq!(path_exists(&p), true);
+            Some(true)
+        });
+    }
+
+    #[test]
+    fn test_dir_exists_absolute_pass() {
+        let d = get_current_dir().ok();
+        d.and_then(|p| {
+            assert_eq!(dir_exists(&p), true);
+            Some(true)
+        });
+    }
+
+    #[test]
+    fn test_dir_exists_absolute_fail() {
+        let e = get_current_exe().ok();
+        e.and_then(|p| {
+            assert_eq!(dir_exists(&p), false);
+            Some(true)
+        });
+    }
+
+    #[test]
+    fn test_tempdir_create_state() {
+        TempDir::new().ok().and_then(|d| {
+            d.path().to_str().and_then(|p| {
+                assert_eq!(dir_exists(p), true);
+                Some(true)
+            });
+            Some(true)
+        });
+    }
+
+    #[test]
+    fn test_is_same_file_stem() {
+        let file = PathBuf::from("randomtemp");
+        let file_ext = PathBuf::from("randomtemp.exe");
+        let no_file: Option<&PathBuf> = None;
+
+        assert_eq!(is_same_file_stem(Some(&file), Some(&file_ext)), true);
+        assert_eq!(is_same_file_stem(Some(&file), no_file), false);
+        assert_eq!(is_same_file_stem(Some(&file_ext), no_file), false);
+    }
+
+    /// Restores a process-wide env var to whatever it was when captured,
+    /// even if the test panics. `get_pretend_executable` reads `PATH` and
+    /// `RANDOMTEMP_EXECUTABLE` straight from the process environment, so
+    /// this is the minimal way to exercise it here without leaking state
+    /// into whichever other `#[test]` happens to run on the same thread.
+    struct EnvVarGuard {
+        key: &'static str,
+        original: Option<std::ffi::OsString>,
+    }
+
+    impl EnvVarGuard {
+        fn capture(key: &'static str) -> EnvVarGuard {
+            EnvVarGuard {
+                key,
+                original: env::var_os(key),
+            }
+        }
+    }
+
+    impl Drop for EnvVarGuard {
+        fn drop(&mut self) {
+            match &self.original {
+                Some(val) => env::set_var(self.key, val),
+                None => env::remove_var(self.key),
+            }
+        }
+    }
+
+    #[test]
+    fn test_pretend_executable_with_absolute_path() -> Result<(), Box<dyn std::error::Error>> {
+        let _path_guard = EnvVarGuard::capture("PATH");
+        let _executable_guard = EnvVarGuard::capture("RANDOMTEMP_EXECUTABLE");
+
+        let executable_name = if cfg!(windows) {
+            "test_randomtemp_absolute.exe"
+        } else {
+            "test_randomtemp_absolute"
+        };
+
+        let tmp_dir_1 = TempDir::new()?;
+        let mut tmp_path_1 = tmp_dir_1.path().to_owned();
+        tmp_path_1.push(executable_name);
+
+        let tmp_dir_2 = TempDir::new()?;
+        let mut tmp_path_2 = tmp_dir_2.path().to_owned();
+        tmp_path_2.push(executable_name);
+
+        {
+            fs::File::create(&tmp_path_1)?;
+            fs::File::create(&tmp_path_2)?;
+        }
+
+        if let Some(path) = env::var_os("PATH") {
+            let mut paths = env::split_paths(&path).collect::<Vec<_>>();
+            paths.push(tmp_dir_1.path().to_path_buf());
+            paths.push(tmp_dir_2.path().to_path_buf());
+            let new_path = env::join_paths(paths)?;
+            env::set_var("PATH", &new_path);
+        }
+
+        let actual_executable = tmp_path_2.to_str().unwrap();
+        env::set_var("RANDOMTEMP_EXECUTABLE", actual_executable);
+        let pred_executable = get_pretend_executable().unwrap();
+        assert_eq!(actual_executable, pred_executable);
+        Ok(())
+    }
+}
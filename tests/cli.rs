@@ -0,0 +1,349 @@
+mod util;
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use util::WorkDir;
+
+#[cfg(windows)]
+const ENV_COMMAND: &str = "set";
+
+#[cfg(not(windows))]
+const ENV_COMMAND: &str = "env";
+
+#[test]
+fn test_executable_with_env() {
+    let wd = WorkDir::new("executable_with_env");
+
+    let original_env = if cfg!(windows) {
+        Command::new("cmd")
+            .arg("/q")
+            .arg("/c")
+            .arg(ENV_COMMAND)
+            .current_dir(wd.path())
+            .output()
+            .expect("failed to execute process")
+    } else {
+        Command::new("sh")
+            .arg("-c")
+            .arg(ENV_COMMAND)
+            .current_dir(wd.path())
+            .output()
+            .expect("failed to execute process")
+    };
+
+    let new_env = wd
+        .command()
+        .env("RANDOMTEMP_EXECUTABLE", ENV_COMMAND)
+        .output()
+        .expect("failed to execute process");
+
+    let original_output = original_env.stdout;
+    let new_output = new_env.stdout;
+    let original_error = original_env.stderr;
+    let new_error = new_env.stderr;
+
+    assert_ne!(original_output, new_output);
+    assert_ne!(original_output.len(), 0);
+    assert_ne!(new_output.len(), 0);
+    assert_eq!(original_error.len(), 0);
+    assert_eq!(new_error.len(), 0);
+    assert_eq!(original_env.status.success(), true);
+    assert_eq!(new_env.status.success(), true);
+}
+
+#[test]
+fn test_executable_with_invalid_max_retry_times() {
+    let wd = WorkDir::new("invalid_max_retry_times");
+
+    let new_env = wd
+        .command()
+        .env("RANDOMTEMP_EXECUTABLE", ENV_COMMAND)
+        .env("RANDOMTEMP_MAXTRIAL", "-1")
+        .output()
+        .expect("failed to execute process");
+
+    assert_eq!(new_env.stdout.len(), 0);
+    assert_ne!(new_env.stderr.len(), 0);
+    assert_eq!(new_env.status.success(), false);
+}
+
+#[test]
+fn test_executable_with_invalid_base_dir() {
+    let wd = WorkDir::new("invalid_base_dir");
+
+    let new_env = wd
+        .command()
+        .env("RANDOMTEMP_EXECUTABLE", ENV_COMMAND)
+        .env("RANDOMTEMP_BASEDIR", "randomtemp")
+        .output()
+        .expect("failed to execute process");
+
+    assert_eq!(new_env.stdout.len(), 0);
+    assert_ne!(new_env.stderr.len(), 0);
+    assert_eq!(new_env.status.success(), false);
+}
+
+#[test]
+fn test_executable_with_no_pretend_self_with_env() {
+    let wd = WorkDir::new("no_pretend_self_with_env");
+
+    let new_env = wd
+        .command()
+        .env("RANDOMTEMP_EXECUTABLE", "randomtemp")
+        .output()
+        .expect("failed to execute process");
+
+    assert_eq!(new_env.stdout.len(), 0);
+    assert_ne!(new_env.stderr.len(), 0);
+    assert_eq!(new_env.status.success(), false);
+}
+
+#[test]
+fn test_executable_with_no_pretend_self_with_name() {
+    let wd = WorkDir::new("no_pretend_self_with_name");
+
+    let new_env = wd.command().output().expect("failed to execute process");
+
+    assert_eq!(new_env.stdout.len(), 0);
+    assert_ne!(new_env.stderr.len(), 0);
+    assert_eq!(new_env.status.success(), false);
+}
+
+#[cfg(not(windows))]
+#[test]
+fn test_executable_found_via_workdir_path() {
+    let wd = WorkDir::new("executable_found_via_workdir_path");
+    wd.create_exe(ENV_COMMAND, 0);
+
+    let new_env = wd
+        .command()
+        .env("RANDOMTEMP_EXECUTABLE", ENV_COMMAND)
+        .output()
+        .expect("failed to execute process");
+
+    assert_eq!(new_env.status.success(), true);
+}
+
+#[cfg(not(windows))]
+#[test]
+fn test_executable_with_custom_envvar() {
+    let wd = WorkDir::new("custom_envvar");
+    let exe = wd.create_script_exe(
+        "dump",
+        "#!/bin/sh\necho \"NVCC_TEMP=$NVCC_TEMP\"\nexit 0\n",
+    );
+
+    let new_env = wd
+        .command()
+        .env("RANDOMTEMP_EXECUTABLE", exe)
+        .env("RANDOMTEMP_ENVVARS", "NVCC_TEMP")
+        .output()
+        .expect("failed to execute process");
+
+    assert_eq!(new_env.status.success(), true);
+    let stdout = String::from_utf8_lossy(&new_env.stdout);
+    let value = stdout
+        .trim()
+        .strip_prefix("NVCC_TEMP=")
+        .expect("stub should print NVCC_TEMP");
+    // The tmp dir is created under the current dir (this WorkDir) and is
+    // gone by the time the child exits successfully, so just check it was
+    // pointed under here rather than asserting it still exists.
+    assert!(
+        Path::new(value).starts_with(wd.path()),
+        "expected NVCC_TEMP under {}, got {:?}",
+        wd.path().display(),
+        value
+    );
+}
+
+#[cfg(not(windows))]
+#[test]
+fn test_executable_with_envvar_arg_template() {
+    let wd = WorkDir::new("envvar_arg_template");
+    let exe = wd.create_script_exe("dump", "#!/bin/sh\necho \"args=$@\"\nexit 0\n");
+
+    let new_env = wd
+        .command()
+        .env("RANDOMTEMP_EXECUTABLE", exe)
+        .env("RANDOMTEMP_ENVVARS", "--tmp={}")
+        .output()
+        .expect("failed to execute process");
+
+    assert_eq!(new_env.status.success(), true);
+    let stdout = String::from_utf8_lossy(&new_env.stdout);
+    let line = stdout.trim();
+    let args = line
+        .strip_prefix("args=")
+        .expect("stub should print its args");
+    assert!(
+        args.starts_with("--tmp=") && args.len() > "--tmp=".len(),
+        "expected a --tmp=<path> arg, got: {:?}",
+        args
+    );
+}
+
+#[cfg(not(windows))]
+#[test]
+fn test_executable_with_default_envvars_falls_back_to_tmpdir() {
+    let wd = WorkDir::new("default_envvars_fallback");
+    let exe = wd.create_script_exe("dump", "#!/bin/sh\necho \"TMPDIR=$TMPDIR\"\nexit 0\n");
+
+    let new_env = wd
+        .command()
+        .env("RANDOMTEMP_EXECUTABLE", exe)
+        .output()
+        .expect("failed to execute process");
+
+    assert_eq!(new_env.status.success(), true);
+    let stdout = String::from_utf8_lossy(&new_env.stdout);
+    let value = stdout
+        .trim()
+        .strip_prefix("TMPDIR=")
+        .expect("stub should print TMPDIR");
+    assert!(!value.is_empty());
+}
+
+#[cfg(not(windows))]
+#[test]
+fn test_executable_with_non_retryable_code_stops_immediately() {
+    let wd = WorkDir::new("non_retryable_code_stops_immediately");
+    let exe = wd.create_exe("fails", 42);
+
+    let new_env = wd
+        .command()
+        .env("RANDOMTEMP_EXECUTABLE", exe)
+        .env("RANDOMTEMP_MAXTRIAL", "3")
+        .env("RANDOMTEMP_RETRY_CODES", "1,2,3")
+        .output()
+        .expect("failed to execute process");
+
+    assert_eq!(new_env.status.code(), Some(42));
+    assert!(!String::from_utf8_lossy(&new_env.stdout).contains("Retry attempt"));
+}
+
+#[cfg(not(windows))]
+#[test]
+fn test_executable_with_retryable_code_retries_until_max_trial() {
+    let wd = WorkDir::new("retryable_code_retries_until_max_trial");
+    let exe = wd.create_exe("fails", 42);
+
+    let new_env = wd
+        .command()
+        .env("RANDOMTEMP_EXECUTABLE", exe)
+        .env("RANDOMTEMP_MAXTRIAL", "2")
+        .env("RANDOMTEMP_RETRY_CODES", "42")
+        .output()
+        .expect("failed to execute process");
+
+    assert_eq!(new_env.status.code(), Some(42));
+    let stdout = String::from_utf8_lossy(&new_env.stdout);
+    assert!(stdout.contains("Retry attempt: 1"));
+    assert!(stdout.contains("Retry attempt: 2"));
+}
+
+#[cfg(not(windows))]
+#[test]
+fn test_executable_with_envvar_arg_template_via_relative_executable() {
+    let wd = WorkDir::new("envvar_arg_template_via_relative_executable");
+    wd.create_script_exe("dump", "#!/bin/sh\necho \"args=$@\"\nexit 0\n");
+
+    // A relative path (as opposed to the absolute path `create_script_exe`
+    // returns, used by `test_executable_with_envvar_arg_template`) isn't
+    // resolved to an absolute one, so this takes the `sh -c` spawn path
+    // instead of spawning the executable directly.
+    let new_env = wd
+        .command()
+        .env("RANDOMTEMP_EXECUTABLE", "./dump")
+        .env("RANDOMTEMP_ENVVARS", "--tmp={}")
+        .output()
+        .expect("failed to execute process");
+
+    assert_eq!(new_env.status.success(), true);
+    let stdout = String::from_utf8_lossy(&new_env.stdout);
+    let line = stdout.trim();
+    let args = line
+        .strip_prefix("args=")
+        .expect("stub should print its args");
+    assert!(
+        args.starts_with("--tmp=") && args.len() > "--tmp=".len(),
+        "expected a --tmp=<path> arg, got: {:?}",
+        args
+    );
+}
+
+#[cfg(not(windows))]
+#[test]
+fn test_executable_with_keep_on_failure_preserves_temp_dir() {
+    let wd = WorkDir::new("keep_on_failure_preserves_temp_dir");
+    let exe = wd.create_script_exe(
+        "fails",
+        "#!/bin/sh\ntouch \"$NVCC_TEMP/marker\"\nexit 1\n",
+    );
+
+    let new_env = wd
+        .command()
+        .env("RANDOMTEMP_EXECUTABLE", exe)
+        .env("RANDOMTEMP_ENVVARS", "NVCC_TEMP")
+        .env("RANDOMTEMP_KEEP", "1")
+        .output()
+        .expect("failed to execute process");
+
+    assert_eq!(new_env.status.success(), false);
+    let stderr = String::from_utf8_lossy(&new_env.stderr);
+    let kept_dir = stderr
+        .lines()
+        .find_map(|line| line.strip_prefix("Keeping temporary directory: "))
+        .expect("stderr should report the kept directory");
+    assert!(
+        Path::new(kept_dir).join("marker").exists(),
+        "expected marker file to survive under {}",
+        kept_dir
+    );
+}
+
+#[cfg(not(windows))]
+#[test]
+fn test_executable_without_keep_on_failure_removes_temp_dir() {
+    let wd = WorkDir::new("without_keep_on_failure_removes_temp_dir");
+    let exe = wd.create_script_exe(
+        "fails",
+        "#!/bin/sh\necho \"$NVCC_TEMP\" > marker_dir\ntouch \"$NVCC_TEMP/marker\"\nexit 1\n",
+    );
+
+    let new_env = wd
+        .command()
+        .env("RANDOMTEMP_EXECUTABLE", exe)
+        .env("RANDOMTEMP_ENVVARS", "NVCC_TEMP")
+        .output()
+        .expect("failed to execute process");
+
+    assert_eq!(new_env.status.success(), false);
+    assert!(!String::from_utf8_lossy(&new_env.stderr).contains("Keeping temporary directory"));
+    let marker_dir = fs::read_to_string(wd.path().join("marker_dir"))
+        .expect("stub should have recorded its temp dir");
+    assert!(
+        !Path::new(marker_dir.trim()).exists(),
+        "expected temp dir {} to be cleaned up",
+        marker_dir.trim()
+    );
+}
+
+#[cfg(windows)]
+#[test]
+fn test_executable_with_shell_quoting_windows() {
+    let wd = WorkDir::new("shell_quoting_windows");
+
+    let new_env = wd
+        .command()
+        .env("RANDOMTEMP_EXECUTABLE", "dir")
+        .arg("C:\\Program Files")
+        .output()
+        .expect("failed to execute process");
+
+    assert_ne!(new_env.stdout.len(), 0);
+    assert_eq!(new_env.stderr.len(), 0);
+    assert_eq!(new_env.status.success(), true);
+}
@@ -0,0 +1,116 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Modeled on ripgrep's own test harness (`tests/util.rs`): every `WorkDir`
+// gets a uniquely-numbered directory so tests that spawn `randomtemp` can run
+// with `--test-threads` greater than one without stepping on each other's
+// `PATH`, cwd or `RANDOMTEMP_*` environment.
+static TEST_DIR_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// A private, uniquely-named scratch directory for a single test.
+pub struct WorkDir {
+    dir: PathBuf,
+}
+
+impl WorkDir {
+    /// Creates a fresh scratch directory for `test_name`, under the test
+    /// binary's parent directory.
+    pub fn new(test_name: &str) -> WorkDir {
+        let id = TEST_DIR_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let mut dir = test_binary_dir();
+        dir.push("rt-tests");
+        dir.push(format!("{}-{}", test_name, id));
+        fs::create_dir_all(&dir)
+            .unwrap_or_else(|e| panic!("failed to create {}: {}", dir.display(), e));
+        WorkDir { dir }
+    }
+
+    /// The scratch directory's path.
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Drops an executable stub named `name` into this directory that exits
+    /// with `exit_code`, and returns its path. On Unix it is made
+    /// executable; on Windows an `.exe` extension is appended.
+    pub fn create_exe(&self, name: &str, exit_code: i32) -> PathBuf {
+        self.create_script_exe(name, &format!("#!/bin/sh\nexit {}\n", exit_code))
+    }
+
+    /// Drops an executable stub named `name` running the given `sh` script
+    /// body into this directory, and returns its path. On Unix it is made
+    /// executable; on Windows an `.exe` extension is appended (the script
+    /// body is ignored there, since `cmd` can't run a shebang script).
+    pub fn create_script_exe(&self, name: &str, script: &str) -> PathBuf {
+        let file_name = if cfg!(windows) {
+            format!("{}.exe", name)
+        } else {
+            name.to_string()
+        };
+        let path = self.dir.join(file_name);
+        let contents = if cfg!(unix) { script } else { "" };
+        fs::write(&path, contents)
+            .unwrap_or_else(|e| panic!("failed to create {}: {}", path.display(), e));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mut perms = fs::metadata(&path).unwrap().permissions();
+            perms.set_mode(0o755);
+            fs::set_permissions(&path, perms).unwrap();
+        }
+
+        path
+    }
+
+    /// A `randomtemp` command pre-configured to run in this directory: its
+    /// `current_dir` is this directory, its `PATH` has this directory
+    /// prepended to the inherited `PATH`, and all `RANDOMTEMP_*` variables
+    /// are cleared so tests start from a clean slate.
+    pub fn command(&self) -> Command {
+        let mut cmd = Command::new(randomtemp_exe());
+        cmd.current_dir(&self.dir);
+
+        let mut paths = vec![self.dir.clone()];
+        if let Some(existing) = env::var_os("PATH") {
+            paths.extend(env::split_paths(&existing));
+        }
+        cmd.env("PATH", env::join_paths(paths).unwrap());
+
+        // Strip every RANDOMTEMP_* var rather than an enumerated list, so a
+        // var ambient in the dev/CI environment can never leak into a test
+        // and a future new RANDOMTEMP_* var can't be forgotten here.
+        for (key, _) in env::vars() {
+            if key.starts_with("RANDOMTEMP_") {
+                cmd.env_remove(key);
+            }
+        }
+
+        cmd
+    }
+}
+
+/// The directory containing the compiled test binaries (e.g.
+/// `target/debug/deps`), used as the root all `WorkDir`s are created under.
+fn test_binary_dir() -> PathBuf {
+    let mut dir = env::current_exe().expect("failed to get current test binary path");
+    dir.pop(); // test binary file name
+    dir
+}
+
+/// Path to the `randomtemp` binary under test.
+fn randomtemp_exe() -> PathBuf {
+    let mut dir = test_binary_dir();
+    if dir.ends_with("deps") {
+        dir.pop();
+    }
+    let exe_name = if cfg!(windows) {
+        "randomtemp.exe"
+    } else {
+        "randomtemp"
+    };
+    dir.join(exe_name)
+}